@@ -0,0 +1,127 @@
+//! Cycle-accurate timestamps and delays backed by the Cortex-M DWT cycle counter
+//!
+//! `CYCCNT` is a free-running 32 bit counter clocked at `sysclk`, making it a much cheaper (and
+//! more precise) timestamp source for profiling trace output than anything derived from a
+//! peripheral timer.
+
+use cortex_m::peripheral::{DWT, DCB};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+use crate::rcc::Clocks;
+use crate::time::Hertz;
+
+/// Owns the DWT unit and enables its free-running cycle counter
+pub struct Dwt {
+    #[allow(dead_code)]
+    dwt: DWT,
+}
+
+impl Dwt {
+    /// Enable `DEMCR.TRCENA` and `DWT_CTRL.CYCCNTENA`, starting the cycle counter from whatever
+    /// value `CYCCNT` already holds (it is not reset)
+    pub fn new(mut dwt: DWT, dcb: &mut DCB) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+        Dwt { dwt }
+    }
+
+    /// Consume this handle to build a [`MonoTimer`] ticking at `clocks.sysclk()`
+    pub fn into_monotonic(self, clocks: &Clocks) -> MonoTimer {
+        MonoTimer::new(self, clocks)
+    }
+}
+
+/// A monotonic, cycle-accurate timestamp source
+///
+/// Built from a [`Dwt`] and the [`Clocks`] in effect when it was created; the stored `sysclk` is
+/// only as accurate as the clock configuration at construction time; reconfiguring clocks at
+/// runtime invalidates `elapsed()` conversions to time the same way it does for
+/// [`swo`](crate::swo).
+pub struct MonoTimer {
+    #[allow(dead_code)]
+    dwt: Dwt,
+    sysclk: Hertz,
+}
+
+impl MonoTimer {
+    /// Create a `MonoTimer` from an enabled `Dwt` and the clocks it should be timed against
+    pub fn new(dwt: Dwt, clocks: &Clocks) -> Self {
+        MonoTimer {
+            dwt,
+            sysclk: clocks.sysclk(),
+        }
+    }
+
+    /// Returns the frequency the cycle counter runs at
+    pub fn frequency(&self) -> Hertz {
+        self.sysclk
+    }
+
+    /// Returns an [`Instant`] corresponding to "now"
+    pub fn now(&self) -> Instant {
+        Instant {
+            cycles: DWT::cycle_count(),
+        }
+    }
+}
+
+/// A snapshot of the DWT cycle counter at a point in time
+#[derive(Clone, Copy)]
+pub struct Instant {
+    cycles: u32,
+}
+
+impl Instant {
+    /// Number of cycles elapsed since this `Instant` was taken, handling a single 32 bit
+    /// wraparound of `CYCCNT` via wrapping subtraction
+    pub fn elapsed(&self) -> u32 {
+        DWT::cycle_count().wrapping_sub(self.cycles)
+    }
+
+    /// Convenience to convert `elapsed()` cycles into nanoseconds for the given `MonoTimer`
+    pub fn elapsed_ns(&self, timer: &MonoTimer) -> u64 {
+        cycles_to_ns(self.elapsed(), timer.sysclk)
+    }
+}
+
+fn cycles_to_ns(cycles: u32, sysclk: Hertz) -> u64 {
+    (u64::from(cycles) * 1_000_000_000) / u64::from(sysclk.raw())
+}
+
+/// Blocking delay driven by the DWT cycle counter, giving sub-microsecond accurate delays tied
+/// to the real core clock rather than a peripheral timer's prescaler granularity
+pub struct DwtDelay {
+    #[allow(dead_code)]
+    dwt: DWT,
+    sysclk: Hertz,
+}
+
+impl DwtDelay {
+    /// Create a new delay provider, enabling the cycle counter if it is not already running
+    pub fn new(mut dwt: DWT, dcb: &mut DCB, clocks: &Clocks) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+        DwtDelay {
+            dwt,
+            sysclk: clocks.sysclk(),
+        }
+    }
+
+    fn delay_cycles(&self, cycles: u32) {
+        let start = DWT::cycle_count();
+        while DWT::cycle_count().wrapping_sub(start) < cycles {}
+    }
+}
+
+impl DelayUs<u32> for DwtDelay {
+    fn delay_us(&mut self, us: u32) {
+        let cycles = (u64::from(us) * u64::from(self.sysclk.raw()) / 1_000_000) as u32;
+        self.delay_cycles(cycles);
+    }
+}
+
+impl DelayMs<u32> for DwtDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_us(ms.saturating_mul(1_000));
+    }
+}
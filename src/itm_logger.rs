@@ -0,0 +1,147 @@
+//! `log`/`defmt` global logger backend that streams records over an ITM stimulus port
+//!
+//! Enabled with the `itm-logger` Cargo feature. Once [`init`] is called with a configured
+//! [`Swo`](crate::swo::Swo) handle, ordinary `log::info!()` calls (or, with the `defmt` feature
+//! also enabled, `defmt::info!()` calls) are buffered per-record and pushed word-at-a-time into
+//! the chosen stimulus port's FIFO, respecting its busy/ready flag. Each record first checks
+//! `DEMCR.TRCENA` and `ITM_TCR.ITMENA`; if either is clear, the record is dropped instead of
+//! waiting on a stimulus FIFO that is not being drained. Both bits are set by the
+//! [`Swo`](crate::swo::Swo) handle that [`init`] takes, whether or not a probe is attached.
+
+use core::cell::RefCell;
+use core::convert::TryInto;
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::itm::Stim;
+#[cfg(not(feature = "defmt"))]
+use heapless::String;
+
+use crate::swo::Swo;
+
+const DEMCR: *const u32 = 0xE000_EDFC as *const u32;
+const DEMCR_TRCENA: u32 = 1 << 24;
+const ITM_TCR: *const u32 = 0xE000_0E80 as *const u32;
+const ITM_TCR_ITMENA: u32 = 1;
+
+// `*mut Stim` is `!Send`, which would make `RefCell<Option<*mut Stim>>` (and therefore this
+// `static`) `!Sync`. Store the pointer's address instead - a plain `usize` is `Send`/`Sync` -
+// and reconstruct the pointer only inside the critical section in `write_record`.
+static STIM: Mutex<RefCell<Option<usize>>> = Mutex::new(RefCell::new(None));
+
+fn debug_unit_enabled() -> bool {
+    unsafe {
+        core::ptr::read_volatile(DEMCR) & DEMCR_TRCENA != 0
+            && core::ptr::read_volatile(ITM_TCR) & ITM_TCR_ITMENA != 0
+    }
+}
+
+/// Push `bytes` into the registered stimulus port, no-op if the trace unit or the ITM is not
+/// enabled
+///
+/// Full 4-byte chunks go out as a single `write_u32`; a trailing 1-3 byte remainder is flushed
+/// byte-by-byte with `write_u8` rather than zero-padded to a word - padding would inject stray
+/// NUL bytes into the stream, which corrupts `defmt`'s rzCOBS framing (`0x00` is its frame
+/// delimiter) and tacks garbage onto `log` lines whose length isn't a multiple of 4.
+fn write_record(bytes: &[u8]) {
+    if !debug_unit_enabled() {
+        return;
+    }
+
+    interrupt::free(|cs| {
+        if let Some(addr) = *STIM.borrow(cs).borrow() {
+            // Safety: `addr` was obtained from a `Swo` whose `ITM` we took ownership of in
+            // `init`, and access is serialized by the critical section.
+            let stim = unsafe { &mut *(addr as *mut Stim) };
+            let mut chunks = bytes.chunks_exact(4);
+            for word in &mut chunks {
+                while !stim.is_fifo_ready() {}
+                stim.write_u32(u32::from_le_bytes(word.try_into().unwrap()));
+            }
+            for byte in chunks.remainder() {
+                while !stim.is_fifo_ready() {}
+                stim.write_u8(*byte);
+            }
+        }
+    });
+}
+
+/// Register `swo`'s stimulus port `port` as the destination for `log`/`defmt` output
+///
+/// `swo` is consumed: the `Stim` handle this stores is just a typed pointer into the ITM's
+/// fixed MMIO address range, so it stays valid for the `'static` lifetime the logging macros
+/// need regardless of where `swo` itself ends up - dropping it normally is fine.
+pub fn init(mut swo: Swo, port: usize) {
+    let stim: *mut Stim = swo.stim(port);
+    interrupt::free(|cs| {
+        *STIM.borrow(cs).borrow_mut() = Some(stim as usize);
+    });
+
+    #[cfg(not(feature = "defmt"))]
+    {
+        log::set_logger(&LOG_LOGGER).ok();
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+}
+
+#[cfg(not(feature = "defmt"))]
+struct ItmLogLogger;
+
+#[cfg(not(feature = "defmt"))]
+static LOG_LOGGER: ItmLogLogger = ItmLogLogger;
+
+#[cfg(not(feature = "defmt"))]
+impl log::Log for ItmLogLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        use core::fmt::Write;
+
+        let mut line: String<256> = String::new();
+        if write!(line, "[{}] {}\r\n", record.level(), record.args()).is_ok() {
+            write_record(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "defmt")]
+mod defmt_backend {
+    use core::cell::RefCell;
+
+    use cortex_m::interrupt::{self, Mutex};
+
+    use super::write_record;
+
+    #[defmt::global_logger]
+    struct ItmDefmtLogger;
+
+    // Guarded the same way as the `log` backend's `write_record` call: `acquire`/`write`/
+    // `release` can otherwise run concurrently from interrupt and thread context, racing on
+    // `ENCODER`'s internal state.
+    static ENCODER: Mutex<RefCell<defmt::Encoder>> = Mutex::new(RefCell::new(defmt::Encoder::new()));
+
+    unsafe impl defmt::Logger for ItmDefmtLogger {
+        fn acquire() {
+            interrupt::free(|cs| {
+                ENCODER.borrow(cs).borrow_mut().start_frame(write_record);
+            });
+        }
+
+        unsafe fn flush() {}
+
+        unsafe fn release() {
+            interrupt::free(|cs| {
+                ENCODER.borrow(cs).borrow_mut().end_frame(write_record);
+            });
+        }
+
+        unsafe fn write(bytes: &[u8]) {
+            interrupt::free(|cs| {
+                ENCODER.borrow(cs).borrow_mut().write(bytes, write_record);
+            });
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! HAL for the STM32F4xx family of microcontrollers
+//!
+//! This crate is a layer on top of [`stm32f4`] that provides a more user friendly API to work
+//! with the peripherals found on the STM32F4xx family of microcontrollers.
+
+#![no_std]
+
+#[cfg(not(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f410",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479",
+)))]
+compile_error!(
+    "This crate requires one of the following device features enabled:
+        stm32f401
+        stm32f405
+        stm32f407
+        stm32f410
+        stm32f411
+        stm32f412
+        stm32f413
+        stm32f415
+        stm32f417
+        stm32f423
+        stm32f427
+        stm32f429
+        stm32f437
+        stm32f439
+        stm32f446
+        stm32f469
+        stm32f479"
+);
+
+#[cfg(feature = "stm32f401")]
+pub use stm32f4::stm32f401 as stm32;
+
+#[cfg(any(feature = "stm32f405", feature = "stm32f415"))]
+pub use stm32f4::stm32f405 as stm32;
+
+#[cfg(any(feature = "stm32f407", feature = "stm32f417"))]
+pub use stm32f4::stm32f407 as stm32;
+
+#[cfg(feature = "stm32f410")]
+pub use stm32f4::stm32f410 as stm32;
+
+#[cfg(feature = "stm32f411")]
+pub use stm32f4::stm32f411 as stm32;
+
+#[cfg(feature = "stm32f412")]
+pub use stm32f4::stm32f412 as stm32;
+
+#[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
+pub use stm32f4::stm32f413 as stm32;
+
+#[cfg(any(feature = "stm32f427", feature = "stm32f437"))]
+pub use stm32f4::stm32f427 as stm32;
+
+#[cfg(any(feature = "stm32f429", feature = "stm32f439"))]
+pub use stm32f4::stm32f429 as stm32;
+
+#[cfg(feature = "stm32f446")]
+pub use stm32f4::stm32f446 as stm32;
+
+#[cfg(any(feature = "stm32f469", feature = "stm32f479"))]
+pub use stm32f4::stm32f469 as stm32;
+
+// Enable use of interrupt macro
+pub use crate::stm32::interrupt;
+
+pub mod dwt;
+#[cfg(feature = "itm-logger")]
+pub mod itm_logger;
+pub mod prelude;
+pub mod rcc;
+pub mod time;
+pub mod swo;
@@ -0,0 +1,5 @@
+//! Prelude
+
+pub use crate::rcc::RccExt as _stm32f4xx_hal_rcc_RccExt;
+#[allow(deprecated)]
+pub use crate::time::U32Ext as _stm32f4xx_hal_time_U32Ext;
@@ -0,0 +1,393 @@
+//! Reset and Clock Control
+
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+/// Extension trait that constrains the `RCC` peripheral
+pub trait RccExt {
+    /// Constrains the `RCC` peripheral so it plays nicely with the other abstractions
+    fn constrain(self) -> Rcc;
+}
+
+impl RccExt for RCC {
+    fn constrain(self) -> Rcc {
+        Rcc {
+            cfgr: CFGR {
+                hse: None,
+                hclk: None,
+                pclk1: None,
+                pclk2: None,
+                sysclk: None,
+                css: false,
+                hse_startup_retries: DEFAULT_HSE_STARTUP_RETRIES,
+            },
+        }
+    }
+}
+
+/// Constrained RCC peripheral
+pub struct Rcc {
+    /// Clock configuration
+    pub cfgr: CFGR,
+}
+
+const HSI: u32 = 16_000_000;
+
+/// Default number of polling iterations [`CFGR::try_freeze`] waits for `RCC_CR.HSERDY` before
+/// giving up, overridden with [`CFGR::hse_startup_retries`]
+const DEFAULT_HSE_STARTUP_RETRIES: u32 = 100_000;
+
+/// Errors that can be returned by [`CFGR::try_freeze`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockError {
+    /// The external oscillator (HSE) did not assert `HSERDY` within the configured number of
+    /// polling iterations, e.g. because it failed to start or is not fitted
+    HseTimeout,
+}
+
+/// Clock configuration builder, reached through `RCC.constrain().cfgr`
+pub struct CFGR {
+    hse: Option<u32>,
+    hclk: Option<u32>,
+    pclk1: Option<u32>,
+    pclk2: Option<u32>,
+    sysclk: Option<u32>,
+    css: bool,
+    hse_startup_retries: u32,
+}
+
+/// Reduce any `fugit` rate (`Hertz`/`KiloHertz`/`MegaHertz`/...) to a raw Hz count
+///
+/// Taking the rate generically here, rather than requiring `Into<Hertz>`, sidesteps the orphan
+/// rule: `Hertz`/`MegaHertz` are just aliases of `fugit::Rate<u32, NOM, DENOM>` for different
+/// `NOM`/`DENOM`, so neither this crate nor `fugit` can implement `From<MegaHertz> for Hertz`
+/// without one of the two being a local type.
+fn hz<const NOM: u32, const DENOM: u32>(freq: fugit::Rate<u32, NOM, DENOM>) -> u32 {
+    freq.convert::<1, 1>().raw()
+}
+
+impl CFGR {
+    /// Use an external high speed oscillator (HSE) of the given frequency instead of the
+    /// internal 16 MHz RC oscillator (HSI) as the reference for `sysclk`.
+    pub fn use_hse<const NOM: u32, const DENOM: u32>(
+        mut self,
+        freq: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.hse = Some(hz(freq));
+        self
+    }
+
+    /// Set the desired AHB bus clock (`HCLK`)
+    pub fn hclk<const NOM: u32, const DENOM: u32>(
+        mut self,
+        freq: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.hclk = Some(hz(freq));
+        self
+    }
+
+    /// Set the desired APB1 bus clock (`PCLK1`)
+    pub fn pclk1<const NOM: u32, const DENOM: u32>(
+        mut self,
+        freq: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.pclk1 = Some(hz(freq));
+        self
+    }
+
+    /// Set the desired APB2 bus clock (`PCLK2`)
+    pub fn pclk2<const NOM: u32, const DENOM: u32>(
+        mut self,
+        freq: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.pclk2 = Some(hz(freq));
+        self
+    }
+
+    /// Set the desired core clock (`SYSCLK`)
+    pub fn sysclk<const NOM: u32, const DENOM: u32>(
+        mut self,
+        freq: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.sysclk = Some(hz(freq));
+        self
+    }
+
+    /// Enable the Clock Security System: if a running HSE fails, the hardware automatically
+    /// switches `sysclk` back to the HSI and raises the `CSS` NMI so the failure can be handled
+    /// from [`Css`] instead of the MCU silently running on a dead clock (or, without HSE, not at
+    /// all).
+    pub fn enable_css(mut self) -> Self {
+        self.css = true;
+        self
+    }
+
+    /// Override the number of times [`try_freeze`](Self::try_freeze) polls `RCC_CR.HSERDY`
+    /// before giving up with [`ClockError::HseTimeout`]
+    pub fn hse_startup_retries(mut self, retries: u32) -> Self {
+        self.hse_startup_retries = retries;
+        self
+    }
+
+    /// Returns `(actual sysclk, Some((m, n, p)))` if a PLL is needed to reach the requested
+    /// `sysclk`, or `(actual sysclk, None)` if the oscillator can be used directly.
+    fn pll_setup(&self) -> (u32, Option<(u32, u32, u32)>) {
+        let base = self.hse.unwrap_or(HSI);
+        match self.sysclk {
+            Some(sysclk) if sysclk != base => {
+                // vco = base / m * n, sysclk = vco / p, with m in 2..=63, p in {2, 4, 6, 8}
+                let m = base / 1_000_000;
+                let vco_in = base / m;
+                let mut best: Option<(u32, u32, u32)> = None;
+                for p in [2u32, 4, 6, 8].iter().copied() {
+                    let vco = sysclk * p;
+                    let n = vco / vco_in;
+                    if !(50..=432).contains(&n) {
+                        continue;
+                    }
+                    let actual = vco_in * n / p;
+                    let err = actual.abs_diff(sysclk);
+                    if best.map(|(_, _, e)| err < e).unwrap_or(true) {
+                        best = Some((n, p, err));
+                    }
+                }
+                let (n, p, _) = best.expect("requested sysclk cannot be reached with the PLL");
+                (vco_in * n / p, Some((m, n, p)))
+            }
+            _ => (base, None),
+        }
+    }
+
+    /// Map a PLLP divider (2, 4, 6 or 8) to the `RCC_PLLCFGR.PLLP` field encoding
+    fn pllp_bits(p: u32) -> u8 {
+        match p {
+            2 => 0b00,
+            4 => 0b01,
+            6 => 0b10,
+            8 => 0b11,
+            _ => unreachable!("{}", "pll_setup only ever picks p from {2, 4, 6, 8}"),
+        }
+    }
+
+    /// `FLASH_ACR.LATENCY` wait states required for a given `HCLK`, assuming the conservative
+    /// 2.7-3.6V supply range from the reference manual's AHB frequency/wait-state table
+    fn flash_wait_states(hclk: u32) -> u8 {
+        match hclk {
+            0..=30_000_000 => 0,
+            30_000_001..=60_000_000 => 1,
+            60_000_001..=90_000_000 => 2,
+            90_000_001..=120_000_000 => 3,
+            120_000_001..=150_000_000 => 4,
+            _ => 5,
+        }
+    }
+
+    /// Freeze the clock configuration, making it effective, and return a `Clocks` handle that
+    /// can be used to read back the configured frequencies.
+    ///
+    /// If an external oscillator was requested with [`use_hse`](Self::use_hse) and it never
+    /// asserts `HSERDY`, this spins forever - use [`try_freeze`](Self::try_freeze) for a bounded
+    /// wait instead.
+    pub fn freeze(self) -> Clocks {
+        self.freeze_internal(None)
+            .unwrap_or_else(|_| unreachable!("unbounded wait never times out"))
+    }
+
+    /// Like [`freeze`](Self::freeze), but returns `Err(ClockError::HseTimeout)` instead of
+    /// spinning forever if the external oscillator never asserts `HSERDY` within
+    /// [`hse_startup_retries`](Self::hse_startup_retries) polling iterations.
+    pub fn try_freeze(self) -> Result<Clocks, ClockError> {
+        let retries = self.hse_startup_retries;
+        self.freeze_internal(Some(retries))
+    }
+
+    fn freeze_internal(self, hse_timeout: Option<u32>) -> Result<Clocks, ClockError> {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        if self.hse.is_some() {
+            rcc.cr.modify(|_, w| w.hseon().set_bit());
+            match hse_timeout {
+                None => while rcc.cr.read().hserdy().bit_is_clear() {},
+                Some(retries) => {
+                    let mut remaining = retries;
+                    while rcc.cr.read().hserdy().bit_is_clear() {
+                        if remaining == 0 {
+                            return Err(ClockError::HseTimeout);
+                        }
+                        remaining -= 1;
+                    }
+                }
+            }
+
+            if self.css {
+                rcc.cr.modify(|_, w| w.csson().set_bit());
+            }
+        }
+
+        let (sysclk, pll) = self.pll_setup();
+
+        let hclk = self.hclk.unwrap_or(sysclk);
+        let (hpre_bits, hclk) = match sysclk / hclk {
+            0 => unreachable!(),
+            1 => (0b0111, sysclk),
+            2 => (0b1000, sysclk / 2),
+            3..=5 => (0b1001, sysclk / 4),
+            6..=11 => (0b1010, sysclk / 8),
+            12..=39 => (0b1011, sysclk / 16),
+            40..=95 => (0b1100, sysclk / 64),
+            96..=191 => (0b1101, sysclk / 128),
+            192..=383 => (0b1110, sysclk / 256),
+            _ => (0b1111, sysclk / 512),
+        };
+
+        // Raise the flash latency for the target `HCLK` before the core clock actually speeds
+        // up - on real silicon, switching to a faster `SYSCLK` before `FLASH_ACR.LATENCY` is
+        // wide enough for it hard-faults.
+        let flash = unsafe { &*crate::stm32::FLASH::ptr() };
+        flash
+            .acr
+            .modify(|_, w| w.latency().bits(Self::flash_wait_states(hclk)));
+
+        if let Some((m, n, p)) = pll {
+            rcc.pllcfgr.modify(|_, w| unsafe {
+                w.pllm()
+                    .bits(m as u8)
+                    .plln()
+                    .bits(n as u16)
+                    .pllp()
+                    .bits(Self::pllp_bits(p))
+                    .pllsrc()
+                    .bit(self.hse.is_some())
+            });
+            rcc.cr.modify(|_, w| w.pllon().set_bit());
+            while rcc.cr.read().pllrdy().bit_is_clear() {}
+            rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(0b10) });
+            while rcc.cfgr.read().sws().bits() != 0b10 {}
+        } else if self.hse.is_some() {
+            rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(0b01) });
+            while rcc.cfgr.read().sws().bits() != 0b01 {}
+        }
+
+        let pclk1 = self.pclk1.unwrap_or_else(|| core::cmp::min(hclk, 42_000_000));
+        let (ppre1_bits, ppre1) = match hclk / pclk1 {
+            0 => unreachable!(),
+            1 => (0b011, 1),
+            2 => (0b100, 2),
+            3..=5 => (0b101, 4),
+            6..=11 => (0b110, 8),
+            _ => (0b111, 16),
+        };
+        let pclk1 = hclk / ppre1;
+
+        let pclk2 = self.pclk2.unwrap_or_else(|| core::cmp::min(hclk, 84_000_000));
+        let (ppre2_bits, ppre2) = match hclk / pclk2 {
+            0 => unreachable!(),
+            1 => (0b011, 1),
+            2 => (0b100, 2),
+            3..=5 => (0b101, 4),
+            6..=11 => (0b110, 8),
+            _ => (0b111, 16),
+        };
+        let pclk2 = hclk / ppre2;
+
+        rcc.cfgr.modify(|_, w| unsafe {
+            w.hpre()
+                .bits(hpre_bits)
+                .ppre1()
+                .bits(ppre1_bits)
+                .ppre2()
+                .bits(ppre2_bits)
+        });
+
+        Ok(Clocks {
+            hclk: Hertz::from_raw(hclk),
+            pclk1: Hertz::from_raw(pclk1),
+            pclk2: Hertz::from_raw(pclk2),
+            ppre1: ppre1 as u8,
+            ppre2: ppre2 as u8,
+            sysclk: Hertz::from_raw(sysclk),
+        })
+    }
+}
+
+/// Frozen clock frequencies, produced by [`CFGR::freeze`]
+///
+/// The existence of this value indicates that the clock configuration can no longer be changed
+#[derive(Clone, Copy)]
+pub struct Clocks {
+    hclk: Hertz,
+    pclk1: Hertz,
+    pclk2: Hertz,
+    ppre1: u8,
+    ppre2: u8,
+    sysclk: Hertz,
+}
+
+impl Clocks {
+    /// Returns the frequency of the AHB bus (`HCLK`)
+    pub fn hclk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// Returns the frequency of the APB1 bus (`PCLK1`)
+    pub fn pclk1(&self) -> Hertz {
+        self.pclk1
+    }
+
+    /// Returns the frequency of the APB2 bus (`PCLK2`)
+    pub fn pclk2(&self) -> Hertz {
+        self.pclk2
+    }
+
+    /// Returns the prescaler of the APB1 bus
+    pub fn ppre1(&self) -> u8 {
+        self.ppre1
+    }
+
+    /// Returns the prescaler of the APB2 bus
+    pub fn ppre2(&self) -> u8 {
+        self.ppre2
+    }
+
+    /// Returns the system (core) clock frequency
+    pub fn sysclk(&self) -> Hertz {
+        self.sysclk
+    }
+}
+
+/// Handle to the Clock Security System enabled with [`CFGR::enable_css`]
+///
+/// On an HSE failure the hardware switches `sysclk` back to the HSI and raises the `CSS` NMI;
+/// the NMI handler should check [`Css::failed`] and, if set, fall back to HSI-derived clocks
+/// before calling [`Css::clear_interrupt`].
+pub struct Css {
+    _private: (),
+}
+
+impl Default for Css {
+    fn default() -> Self {
+        Css::new()
+    }
+}
+
+impl Css {
+    /// Obtain the `Css` handle. Only meaningful after `try_freeze`/`freeze` ran with
+    /// [`CFGR::enable_css`] set - otherwise the flag this reads is never raised.
+    pub fn new() -> Self {
+        Css { _private: () }
+    }
+
+    /// Returns `true` if the clock security system NMI has fired, i.e. a running HSE failed
+    pub fn failed(&self) -> bool {
+        let rcc = unsafe { &*RCC::ptr() };
+        rcc.cir.read().cssf().bit_is_set()
+    }
+
+    /// Acknowledge the clock security system failure (`RCC_CIR.CSSC`)
+    pub fn clear_interrupt(&mut self) {
+        let rcc = unsafe { &*RCC::ptr() };
+        // `write` would zero the rest of CIR's interrupt-enable bits; only the CSS flag should
+        // be acknowledged here.
+        rcc.cir.modify(|_, w| w.cssc().set_bit());
+    }
+}
@@ -0,0 +1,195 @@
+//! SWO/ITM trace output
+//!
+//! This module sets up the on-chip "self hosted" SWO trace path described in the
+//! `clocksetup-with-itm-debug` example: it programs the TPIU/ITM/DWT registers directly, so a
+//! debug probe does not need to reconfigure the target, and ties the SWO bit rate to the
+//! [`Clocks`](crate::rcc::Clocks) produced by `rcc.cfgr.freeze()` so it survives a real clock
+//! setup rather than whatever the probe guessed.
+//!
+//! The SWO signal is only available on `PB3` (configured for its trace alternate function);
+//! claiming that pin for any other purpose will silence SWO output. This module does not own a
+//! GPIO handle for it - the crate has no `gpio` module yet - so that conflict is only documented
+//! here, not enforced by the type system.
+
+use cortex_m::peripheral::ITM;
+
+use crate::rcc::Clocks;
+use crate::stm32::DBGMCU;
+use crate::time::Hertz;
+
+const DEMCR: *mut u32 = 0xE000_EDFC as *mut u32;
+const DEMCR_TRCENA: u32 = 1 << 24;
+
+const ITM_LAR: *mut u32 = 0xE000_0FB0 as *mut u32;
+const ITM_LAR_UNLOCK: u32 = 0xC5AC_CE55;
+const ITM_TCR: *mut u32 = 0xE000_0E80 as *mut u32;
+const ITM_TPR: *mut u32 = 0xE000_0E40 as *mut u32;
+const ITM_TER: *mut u32 = 0xE000_0E00 as *mut u32;
+
+const DWT_CTRL: *mut u32 = 0xE000_1000 as *mut u32;
+
+const TPIU_ACPR: *mut u32 = 0xE004_0010 as *mut u32;
+const TPIU_SPPR: *mut u32 = 0xE004_00F0 as *mut u32;
+const TPIU_FFCR: *mut u32 = 0xE004_0304 as *mut u32;
+
+/// SWO pin encoding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SwoMode {
+    /// UART-compatible (NRZ) encoding - widely supported, needs matched baud rates to within ±5%
+    Nrz,
+    /// Manchester encoding - higher data rates, tolerant of mismatched baud rates (±10%)
+    Manchester,
+}
+
+impl SwoMode {
+    fn sppr(self) -> u32 {
+        match self {
+            SwoMode::Nrz => 0x2,
+            SwoMode::Manchester => 0x1,
+        }
+    }
+}
+
+/// Builder for [`Swo`]
+pub struct SwoBuilder {
+    mode: SwoMode,
+    bit_rate: Hertz,
+}
+
+impl SwoBuilder {
+    /// Select the SWO pin encoding, default is [`SwoMode::Nrz`]
+    pub fn mode(mut self, mode: SwoMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Select the desired SWO bit rate, default is 2 MBaud
+    ///
+    /// Taking the rate generically here, rather than requiring `Into<Hertz>`, sidesteps the
+    /// orphan rule the same way `rcc::CFGR`'s setters do: `Hertz`/`MegaHertz` are just aliases of
+    /// `fugit::Rate<u32, NOM, DENOM>` for different `NOM`/`DENOM`, so neither this crate nor
+    /// `fugit` can implement `From<MegaHertz> for Hertz`.
+    pub fn bit_rate<const NOM: u32, const DENOM: u32>(
+        mut self,
+        bit_rate: fugit::Rate<u32, NOM, DENOM>,
+    ) -> Self {
+        self.bit_rate = Hertz::from_raw(bit_rate.convert::<1, 1>().raw());
+        self
+    }
+
+    /// Apply the configuration, taking ownership of `ITM`, `TPIU` and `DBGMCU`
+    ///
+    /// `DEMCR` and `DWT_CTRL` are also written directly, without owning `DCB` or `DWT`; the
+    /// `DWT_CTRL` value written leaves `CYCCNTENA` clear, stopping the cycle counter.
+    ///
+    /// There is no `gpio` module yet to take `PB3` by type, so the caller is responsible for
+    /// having put it in its trace alternate function and not handing it out elsewhere.
+    pub fn finish(
+        self,
+        itm: ITM,
+        tpiu: cortex_m::peripheral::TPIU,
+        dbgmcu: DBGMCU,
+        clocks: &Clocks,
+    ) -> Swo {
+        let mut swo = Swo {
+            itm,
+            tpiu,
+            dbgmcu,
+            mode: self.mode,
+            bit_rate: self.bit_rate,
+        };
+        swo.configure(clocks);
+        swo
+    }
+}
+
+/// SWO/ITM trace output, see the [module docs](self) for how it ties to `Clocks` and `PB3`
+pub struct Swo {
+    itm: ITM,
+    #[allow(dead_code)]
+    tpiu: cortex_m::peripheral::TPIU,
+    #[allow(dead_code)]
+    dbgmcu: DBGMCU,
+    mode: SwoMode,
+    bit_rate: Hertz,
+}
+
+impl Swo {
+    /// Start building a `Swo` instance; `bit_rate` defaults to 2 MBaud and `mode` to
+    /// [`SwoMode::Nrz`] until overridden on the returned builder
+    pub fn builder() -> SwoBuilder {
+        SwoBuilder {
+            mode: SwoMode::Nrz,
+            bit_rate: Hertz::from_raw(2_000_000),
+        }
+    }
+
+    /// Set up SWO trace output using sensible (NRZ, 2 MBaud) defaults
+    pub fn new(itm: ITM, tpiu: cortex_m::peripheral::TPIU, dbgmcu: DBGMCU, clocks: &Clocks) -> Swo {
+        Swo::builder().finish(itm, tpiu, dbgmcu, clocks)
+    }
+
+    fn configure(&mut self, clocks: &Clocks) {
+        unsafe {
+            // Enable the core trace macrocell and ungate the debug blocks.
+            core::ptr::write_volatile(DEMCR, core::ptr::read_volatile(DEMCR) | DEMCR_TRCENA);
+            self.dbgmcu
+                .cr
+                .modify(|_, w| w.trace_ioen().set_bit().trace_mode().bits(0));
+
+            // Unlock the ITM so its registers become writable, then disable it and its stimulus
+            // ports while we reconfigure.
+            core::ptr::write_volatile(ITM_LAR, ITM_LAR_UNLOCK);
+            core::ptr::write_volatile(ITM_TCR, 0);
+            core::ptr::write_volatile(ITM_TER, 0);
+
+            // Select the SWO pin protocol and the prescaler that yields the requested bit rate
+            // from the real `sysclk`.
+            core::ptr::write_volatile(TPIU_SPPR, self.mode.sppr());
+            core::ptr::write_volatile(TPIU_ACPR, Self::prescaler(clocks, self.bit_rate));
+
+            core::ptr::write_volatile(ITM_TPR, 0);
+            core::ptr::write_volatile(DWT_CTRL, 0x4000_03FE);
+            core::ptr::write_volatile(TPIU_FFCR, 0x100);
+
+            // Re-enable ITM (bit 0), local timestamps off, and stimulus port 0; other ports are
+            // enabled as they are borrowed through `stim()`.
+            core::ptr::write_volatile(ITM_TCR, 0x1_000D);
+            core::ptr::write_volatile(ITM_TER, 0x1);
+        }
+    }
+
+    /// Recompute the TPIU prescaler from an updated `Clocks`, so trace survives a runtime
+    /// `freeze()` on a different `sysclk`
+    pub fn reconfigure(&mut self, clocks: &Clocks) {
+        unsafe {
+            core::ptr::write_volatile(TPIU_ACPR, Self::prescaler(clocks, self.bit_rate));
+        }
+    }
+
+    /// `TPIU_ACPR = sysclk / bit_rate - 1`, saturating instead of underflowing when the
+    /// requested `bit_rate` is unreachable (i.e. `>= sysclk`); saturating clamps to the fastest
+    /// achievable rate (`ACPR == 0`, i.e. `bit_rate == sysclk`) rather than panicking/wrapping.
+    fn prescaler(clocks: &Clocks, bit_rate: Hertz) -> u32 {
+        (clocks.sysclk().raw() / bit_rate.raw()).saturating_sub(1)
+    }
+
+    /// Borrow the stimulus port `port` (0..=31) for writing trace data, enabling it in `ITM_TER`
+    pub fn stim(&mut self, port: usize) -> &mut cortex_m::peripheral::itm::Stim {
+        if port < 32 {
+            unsafe {
+                core::ptr::write_volatile(ITM_TER, core::ptr::read_volatile(ITM_TER) | 1 << port);
+            }
+        }
+        &mut self.itm.stim[port]
+    }
+
+    /// Write raw bytes to a stimulus port, busy-waiting on the FIFO ready flag
+    pub fn write_bytes(&mut self, port: usize, bytes: &[u8]) {
+        let stim = self.stim(port);
+        for byte in bytes {
+            while !stim.is_fifo_ready() {}
+            stim.write_u8(*byte);
+        }
+    }
+}
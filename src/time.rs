@@ -0,0 +1,62 @@
+//! Time units
+//!
+//! Clock and baud rates are expressed using [`fugit`]'s checked `Rate` types, so e.g. a
+//! `Clocks::sysclk()` value can be fed directly into the SWO prescaler math or a timer/serial
+//! baud computation without manually unwrapping an inner `u32` first.
+//!
+//! The original `Bps`/`Hertz`/`KiloHertz`/`MegaHertz` names are kept as plain aliases of the
+//! equivalent `fugit` type, and the `.bps()`/`.hz()`/`.khz()`/`.mhz()` extension methods as a
+//! `#[deprecated]` transition shim returning those types, so `.mhz()`-style call sites written
+//! against the pre-`fugit` API keep compiling.
+
+pub use fugit::{HertzU32, KilohertzU32, MegahertzU32, RateExtU32};
+
+/// Clock/signal rate in Hz, alias of [`fugit::HertzU32`]
+pub type Hertz = HertzU32;
+
+/// Clock/signal rate in kHz, alias of [`fugit::KilohertzU32`]
+pub type KiloHertz = KilohertzU32;
+
+/// Clock/signal rate in MHz, alias of [`fugit::MegahertzU32`]
+pub type MegaHertz = MegahertzU32;
+
+/// Baud rate in bits/second
+pub type Bps = HertzU32;
+
+/// Extension trait that adds convenience methods to the `u32` type
+///
+/// Superseded by [`fugit::RateExtU32`] (`.Hz()`/`.kHz()`/`.MHz()`); kept only so existing
+/// `.hz()`/`.khz()`/`.mhz()`/`.bps()` call sites keep compiling.
+#[deprecated(note = "use fugit::RateExtU32 (.Hz()/.kHz()/.MHz()) instead")]
+pub trait U32Ext {
+    /// Wrap in `Bps`
+    fn bps(self) -> Bps;
+
+    /// Wrap in `Hertz`
+    fn hz(self) -> Hertz;
+
+    /// Wrap in `KiloHertz`
+    fn khz(self) -> KiloHertz;
+
+    /// Wrap in `MegaHertz`
+    fn mhz(self) -> MegaHertz;
+}
+
+#[allow(deprecated)]
+impl U32Ext for u32 {
+    fn bps(self) -> Bps {
+        self.Hz()
+    }
+
+    fn hz(self) -> Hertz {
+        self.Hz()
+    }
+
+    fn khz(self) -> KiloHertz {
+        self.kHz()
+    }
+
+    fn mhz(self) -> MegaHertz {
+        self.MHz()
+    }
+}